@@ -0,0 +1,70 @@
+//! A length-prefixed frame reader built on top of [`LengthLimit`], for wire formats that declare
+//! each payload's size up front (for example the Nix daemon protocol).
+
+use crate::{LengthLimit, LengthLimitExceeded};
+use futures_lite::{AsyncRead, AsyncReadExt};
+use std::io::Result;
+
+/// Reads a fixed-width big-endian `u64` length prefix from `reader`, then returns a
+/// [`LengthLimit`] pre-charged to exactly that many bytes, so the payload can never read past the
+/// frame.
+///
+/// If the declared length exceeds `allowed_size`, this returns [`LengthLimitExceeded`] before
+/// reading (or allocating anything for) the payload.
+///
+/// # Examples
+///
+/// ```rust
+/// use async_read_length_limit::read_length_prefixed_frame;
+/// use futures_lite::{io::Cursor, AsyncReadExt};
+///
+/// # futures_lite::future::block_on(async move {
+/// let mut frame = Vec::from(5u64.to_be_bytes());
+/// frame.extend_from_slice(b"hello");
+///
+/// // because the limit is exclusive (see `LengthLimit`), a full frame should be read with a
+/// // sized call such as `read_exact` rather than `read_to_end`
+/// let mut limited = read_length_prefixed_frame(Cursor::new(frame), 1024)
+///     .await
+///     .unwrap();
+/// let mut payload = vec![0; limited.bytes_remaining()];
+/// limited.read_exact(&mut payload).await.unwrap();
+/// assert_eq!(payload, b"hello");
+/// # });
+/// ```
+pub async fn read_length_prefixed_frame<T>(
+    mut reader: T,
+    allowed_size: usize,
+) -> Result<LengthLimit<T>>
+where
+    T: AsyncRead + Unpin,
+{
+    let mut len_bytes = [0u8; 8];
+    reader.read_exact(&mut len_bytes).await?;
+    let len = u64::from_be_bytes(len_bytes);
+
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|&len| len <= allowed_size)
+        .ok_or(LengthLimitExceeded)?;
+
+    Ok(LengthLimit::new(reader, len))
+}
+
+/// Skips the zero-padding that follows a `payload_len`-byte frame in the 8-byte-aligned framing
+/// used by protocols such as Nix's, leaving `reader` positioned at the start of the next frame.
+///
+/// Call this after fully consuming the [`LengthLimit`] returned by [`read_length_prefixed_frame`]
+/// (via [`LengthLimit::into_inner`]) whenever the wire format pads frames to an 8-byte boundary.
+pub async fn skip_frame_padding<T>(mut reader: T, payload_len: usize) -> Result<T>
+where
+    T: AsyncRead + Unpin,
+{
+    let padding = payload_len.next_multiple_of(8) - payload_len;
+    if padding > 0 {
+        let mut pad = [0u8; 8];
+        reader.read_exact(&mut pad[..padding]).await?;
+    }
+
+    Ok(reader)
+}
@@ -12,7 +12,8 @@
 //! # async-read-length-limit
 //!
 //! Protects against a certain class of denial-of-service attacks wherein long chunked bodies are
-//! uploaded to web services. Can be applied to any [`AsyncRead`] type.
+//! uploaded to web services. Can be applied to any [`AsyncRead`] or [`AsyncWrite`] type, bounding
+//! reads and writes alike.
 //!
 //! # Examples
 //!
@@ -39,15 +40,26 @@
 //! # });
 //! ```
 
-use futures_lite::AsyncRead;
+use futures_lite::{AsyncBufRead, AsyncRead, AsyncWrite};
 use std::{
     error::Error,
     fmt::Display,
-    io::{ErrorKind, Result},
+    io::{ErrorKind, IoSliceMut, Result},
     pin::Pin,
     task::{ready, Context, Poll},
 };
 
+mod collect;
+pub use collect::{read_to_end_limited, LengthLimitError};
+
+mod frame;
+pub use frame::{read_length_prefixed_frame, skip_frame_padding};
+
+mod speed_limit;
+pub use speed_limit::{
+    Clock, MockClock, MockSleep, SpeedLimit, SpeedLimitExt, SystemClock, SystemSleep,
+};
+
 pin_project_lite::pin_project! {
     /// # [`AsyncRead`] length limiter
     ///
@@ -64,19 +76,24 @@ pin_project_lite::pin_project! {
         #[pin]
         reader:  T,
         bytes_remaining: usize,
+        max_read_chunk: usize,
     }
 }
 
-impl<T> LengthLimit<T>
-where
-    T: AsyncRead,
-{
-    /// Constructs a new [`LengthLimit`] with provided [`AsyncRead`] reader and `max_bytes` byte
-    /// length
+/// The default value for [`LengthLimit::with_max_read_chunk`], chosen to be large enough to
+/// amortize syscall overhead on a real transfer while staying far below the point where a single
+/// allocation becomes a useful denial-of-service lever.
+const DEFAULT_MAX_READ_CHUNK: usize = 512 * 1024;
+
+impl<T> LengthLimit<T> {
+    /// Constructs a new [`LengthLimit`] with provided `max_bytes` byte length. `reader` may be any
+    /// type, but [`LengthLimit`] only implements [`AsyncRead`] and [`AsyncWrite`] when the
+    /// contained type does
     pub fn new(reader: T, max_bytes: usize) -> Self {
         Self {
             reader,
             bytes_remaining: max_bytes,
+            max_read_chunk: DEFAULT_MAX_READ_CHUNK,
         }
     }
 
@@ -85,6 +102,24 @@ where
         self.bytes_remaining
     }
 
+    /// Returns the configured chunk size used by [`LengthLimit::collect`] (and, by extension,
+    /// [`read_to_end_limited`][crate::read_to_end_limited]) when collecting into a [`Vec`].
+    pub fn max_read_chunk(&self) -> usize {
+        self.max_read_chunk
+    }
+
+    /// Caps the amount of memory [`LengthLimit::collect`] reserves at a time to `max_read_chunk`
+    /// bytes, instead of letting it reserve up front based on the underlying reader's size hint.
+    ///
+    /// This protects against a reader that advertises (via [`AsyncRead::poll_read`]'s buffer size
+    /// or an inflated `Content-Length`) a length far larger than it actually sends, which would
+    /// otherwise force a single large allocation before any of the over-stated data arrives.
+    /// Defaults to 512 KiB.
+    pub fn with_max_read_chunk(mut self, max_read_chunk: usize) -> Self {
+        self.max_read_chunk = max_read_chunk;
+        self
+    }
+
     /// Unwraps the contained AsyncRead, allowing it to be read to completion. bytes remaining data
     /// are discarded
     pub fn into_inner(self) -> T {
@@ -137,6 +172,87 @@ impl<T: AsyncRead> AsyncRead for LengthLimit<T> {
         *projection.bytes_remaining = bytes_remaining.saturating_sub(new_bytes);
         Poll::Ready(Ok(new_bytes))
     }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<Result<usize>> {
+        let projection = self.project();
+        let reader = projection.reader;
+        let mut bytes_remaining = *projection.bytes_remaining;
+
+        if bytes_remaining == 0 {
+            return Poll::Ready(Err(LengthLimitExceeded.into()));
+        }
+
+        let mut truncated = Vec::with_capacity(bufs.len());
+        for buf in bufs.iter_mut() {
+            if bytes_remaining == 0 {
+                break;
+            }
+            let len = buf.len().min(bytes_remaining);
+            truncated.push(IoSliceMut::new(&mut buf[..len]));
+            bytes_remaining -= len;
+        }
+
+        let new_bytes = ready!(reader.poll_read_vectored(cx, &mut truncated))?;
+        *projection.bytes_remaining = (*projection.bytes_remaining).saturating_sub(new_bytes);
+        Poll::Ready(Ok(new_bytes))
+    }
+}
+
+impl<T: AsyncBufRead> AsyncBufRead for LengthLimit<T> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<&[u8]>> {
+        let projection = self.project();
+        let bytes_remaining = *projection.bytes_remaining;
+
+        if bytes_remaining == 0 {
+            return Poll::Ready(Err(LengthLimitExceeded.into()));
+        }
+
+        let buf = ready!(projection.reader.poll_fill_buf(cx))?;
+        let len = buf.len().min(bytes_remaining);
+        Poll::Ready(Ok(&buf[..len]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let projection = self.project();
+        *projection.bytes_remaining = projection.bytes_remaining.saturating_sub(amt);
+        projection.reader.consume(amt);
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for LengthLimit<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut buf: &[u8],
+    ) -> Poll<Result<usize>> {
+        let projection = self.project();
+        let writer = projection.reader;
+        let bytes_remaining = *projection.bytes_remaining;
+
+        if bytes_remaining == 0 {
+            return Poll::Ready(Err(LengthLimitExceeded.into()));
+        }
+
+        if bytes_remaining < buf.len() {
+            buf = &buf[..bytes_remaining];
+        }
+
+        let new_bytes = ready!(writer.poll_write(cx, buf))?;
+        *projection.bytes_remaining = bytes_remaining.saturating_sub(new_bytes);
+        Poll::Ready(Ok(new_bytes))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.project().reader.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.project().reader.poll_close(cx)
+    }
 }
 
 /// Extension trait to add length limiting behavior to any AsyncRead
@@ -168,3 +284,39 @@ pub trait LengthLimitExt: Sized + AsyncRead {
 }
 
 impl<T> LengthLimitExt for T where T: AsyncRead + Unpin {}
+
+/// Extension trait to add length limiting behavior to any AsyncWrite
+///
+/// Full explanation of the behavior at [`LengthLimit`]
+///
+/// The methods here are named `limit_write_*`, rather than reusing the `limit_*` names from
+/// [`LengthLimitExt`], so that a type implementing both [`AsyncRead`] and [`AsyncWrite`] (for
+/// example a duplex stream or an in-memory buffer) can have both extension traits in scope
+/// without an ambiguous method call.
+pub trait LengthLimitWriteExt: Sized + AsyncWrite {
+    /// Applies a LengthLimit to self that lets at most `max_bytes` bytes be written; any further
+    /// write fails
+    fn limit_write_bytes(self, max_bytes: usize) -> LengthLimit<Self> {
+        LengthLimit::new(self, max_bytes)
+    }
+
+    /// Applies a LengthLimit to self that lets at most `max_kb` kilobytes (defined as 1024 bytes)
+    /// be written
+    fn limit_write_kb(self, max_kb: usize) -> LengthLimit<Self> {
+        self.limit_write_bytes(max_kb * 1024)
+    }
+
+    /// Applies a LengthLimit to self that lets at most `max_mb` megabytes (defined as 1024
+    /// kilobytes, or 1,048,576 bytes) be written
+    fn limit_write_mb(self, max_mb: usize) -> LengthLimit<Self> {
+        self.limit_write_kb(max_mb * 1024)
+    }
+
+    /// Applies a LengthLimit to self that lets at most `max_gb` gigabytes (defined as 1024
+    /// megabytes, or 1,073,741,824 bytes) be written
+    fn limit_write_gb(self, max_gb: usize) -> LengthLimit<Self> {
+        self.limit_write_mb(max_gb * 1024)
+    }
+}
+
+impl<T> LengthLimitWriteExt for T where T: AsyncWrite + Unpin {}
@@ -0,0 +1,335 @@
+//! Throughput (bytes per second) limiting, complementing [`crate::LengthLimit`]'s cap on the
+//! total number of bytes. Built as a token bucket driven by a pluggable [`Clock`], so it can run
+//! under any executor (or, in tests, a deterministic [`MockClock`]).
+
+use futures_lite::AsyncRead;
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BinaryHeap, VecDeque},
+    fmt,
+    future::Future,
+    io::Result,
+    pin::Pin,
+    rc::Rc,
+    sync::{Condvar, Mutex, OnceLock},
+    task::{ready, Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+/// Supplies the current time and a way to sleep, so [`SpeedLimit`] isn't tied to any particular
+/// async runtime.
+pub trait Clock {
+    /// The future returned by [`Clock::sleep`].
+    type Sleep: Future<Output = ()>;
+
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+
+    /// Returns a future that resolves once `duration` has elapsed.
+    fn sleep(&self, duration: Duration) -> Self::Sleep;
+}
+
+/// The default [`Clock`], backed by [`std::time::Instant`]. Outstanding [`sleep`][Clock::sleep]s
+/// are serviced by a single lazily-started background thread shared across every [`SystemSleep`]
+/// in the process, rather than a dedicated thread per outstanding sleep, so a large number of
+/// concurrently throttled readers can't be used to exhaust threads.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    type Sleep = SystemSleep;
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        SystemSleep {
+            deadline: Instant::now() + duration,
+        }
+    }
+}
+
+struct TimerEntry {
+    deadline: Instant,
+    waker: Waker,
+}
+
+impl PartialEq for TimerEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for TimerEntry {}
+
+impl PartialOrd for TimerEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed so that `BinaryHeap`, a max-heap, pops the *earliest* deadline first
+        other.deadline.cmp(&self.deadline)
+    }
+}
+
+static TIMER_QUEUE: Mutex<BinaryHeap<TimerEntry>> = Mutex::new(BinaryHeap::new());
+static TIMER_CONDVAR: Condvar = Condvar::new();
+static TIMER_THREAD: OnceLock<()> = OnceLock::new();
+
+fn register_wakeup(deadline: Instant, waker: Waker) {
+    TIMER_THREAD.get_or_init(|| {
+        std::thread::spawn(run_timer_thread);
+    });
+
+    TIMER_QUEUE
+        .lock()
+        .unwrap()
+        .push(TimerEntry { deadline, waker });
+    TIMER_CONDVAR.notify_one();
+}
+
+/// Body of the single background thread that services every [`SystemSleep`] in the process: wait
+/// until the earliest outstanding deadline, wake everything that has elapsed, then sleep again.
+fn run_timer_thread() -> ! {
+    loop {
+        let mut queue = TIMER_QUEUE.lock().unwrap();
+
+        let now = Instant::now();
+        while queue.peek().is_some_and(|entry| entry.deadline <= now) {
+            queue.pop().unwrap().waker.wake();
+        }
+
+        queue = match queue.peek() {
+            Some(entry) => {
+                let timeout = entry.deadline.saturating_duration_since(Instant::now());
+                TIMER_CONDVAR.wait_timeout(queue, timeout).unwrap().0
+            }
+            None => TIMER_CONDVAR.wait(queue).unwrap(),
+        };
+        drop(queue);
+    }
+}
+
+/// [`Future`] returned by [`SystemClock::sleep`].
+#[derive(Debug, Clone, Copy)]
+pub struct SystemSleep {
+    deadline: Instant,
+}
+
+impl Future for SystemSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        register_wakeup(self.deadline, cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// A deterministic [`Clock`] for tests. Time only moves forward when [`MockClock::advance`] is
+/// called, so a test can assert exactly how many bytes a [`SpeedLimit`] permits before and after
+/// a given amount of time passes.
+#[derive(Debug, Clone, Default)]
+pub struct MockClock(Rc<RefCell<MockClockState>>);
+
+#[derive(Debug)]
+struct MockClockState {
+    now: Instant,
+    wakers: VecDeque<Waker>,
+}
+
+impl Default for MockClockState {
+    fn default() -> Self {
+        Self {
+            now: Instant::now(),
+            wakers: VecDeque::new(),
+        }
+    }
+}
+
+impl MockClock {
+    /// Constructs a new [`MockClock`] whose initial time is the moment of construction.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advances the mock clock by `duration`, waking any [`SpeedLimit`] that is currently
+    /// sleeping on this clock so it can re-check whether enough time has passed.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.0.borrow_mut();
+        state.now += duration;
+        for waker in state.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+impl Clock for MockClock {
+    type Sleep = MockSleep;
+
+    fn now(&self) -> Instant {
+        self.0.borrow().now
+    }
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        let deadline = self.0.borrow().now + duration;
+        MockSleep {
+            clock: self.clone(),
+            deadline,
+        }
+    }
+}
+
+/// [`Future`] returned by [`MockClock::sleep`].
+#[derive(Debug, Clone)]
+pub struct MockSleep {
+    clock: MockClock,
+    deadline: Instant,
+}
+
+impl Future for MockSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.clock.0.borrow_mut();
+        if state.now >= self.deadline {
+            Poll::Ready(())
+        } else {
+            state.wakers.push_back(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// # Token-bucket throughput limiter
+    ///
+    /// Unlike [`crate::LengthLimit`], which caps the total number of bytes read, [`SpeedLimit`]
+    /// caps the *rate* at which bytes are read, in bytes per second. It never discards data or
+    /// errors; once the bucket is empty it simply waits for it to refill.
+    pub struct SpeedLimit<T, C: Clock = SystemClock> {
+        #[pin]
+        reader: T,
+        clock: C,
+        capacity: f64,
+        rate: f64,
+        tokens: f64,
+        last_refill: Instant,
+        #[pin]
+        sleeping: Option<C::Sleep>,
+    }
+}
+
+impl<T> SpeedLimit<T, SystemClock>
+where
+    T: AsyncRead,
+{
+    /// Constructs a new [`SpeedLimit`] that throttles `reader` to `bytes_per_second` bytes per
+    /// second, using the system clock
+    pub fn new(reader: T, bytes_per_second: usize) -> Self {
+        Self::with_clock(reader, bytes_per_second, SystemClock)
+    }
+}
+
+impl<T, C> SpeedLimit<T, C>
+where
+    T: AsyncRead,
+    C: Clock,
+{
+    /// Constructs a new [`SpeedLimit`] that throttles `reader` to `bytes_per_second` bytes per
+    /// second, driven by the provided [`Clock`]
+    pub fn with_clock(reader: T, bytes_per_second: usize, clock: C) -> Self {
+        assert!(
+            bytes_per_second > 0,
+            "bytes_per_second must be greater than zero"
+        );
+        let rate = bytes_per_second as f64;
+        Self {
+            last_refill: clock.now(),
+            reader,
+            clock,
+            capacity: rate,
+            rate,
+            tokens: rate,
+            sleeping: None,
+        }
+    }
+
+    /// Unwraps the contained reader
+    pub fn into_inner(self) -> T {
+        self.reader
+    }
+}
+
+impl<T: fmt::Debug, C: Clock + fmt::Debug> fmt::Debug for SpeedLimit<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpeedLimit")
+            .field("reader", &self.reader)
+            .field("clock", &self.clock)
+            .field("capacity", &self.capacity)
+            .field("rate", &self.rate)
+            .field("tokens", &self.tokens)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, C> AsyncRead for SpeedLimit<T, C>
+where
+    T: AsyncRead,
+    C: Clock,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(sleep) = this.sleeping.as_mut().as_pin_mut() {
+                ready!(sleep.poll(cx));
+                this.sleeping.set(None);
+            }
+
+            let now = this.clock.now();
+            let elapsed = now
+                .saturating_duration_since(*this.last_refill)
+                .as_secs_f64();
+            *this.last_refill = now;
+            *this.tokens = (*this.tokens + elapsed * *this.rate).min(*this.capacity);
+
+            if *this.tokens >= 1.0 {
+                break;
+            }
+
+            let wait = Duration::from_secs_f64((1.0 - *this.tokens) / *this.rate);
+            this.sleeping.set(Some(this.clock.sleep(wait)));
+        }
+
+        let allowed = (*this.tokens as usize).min(buf.len());
+        let new_bytes = ready!(this.reader.poll_read(cx, &mut buf[..allowed]))?;
+        *this.tokens -= new_bytes as f64;
+        Poll::Ready(Ok(new_bytes))
+    }
+}
+
+/// Extension trait to add throughput limiting behavior to any AsyncRead
+///
+/// Full explanation of the behavior at [`SpeedLimit`]
+pub trait SpeedLimitExt: Sized + AsyncRead {
+    /// Applies a [`SpeedLimit`] to self, throttling reads to at most `bytes_per_second` bytes per
+    /// second, using the system clock
+    fn limit_bytes_per_second(self, bytes_per_second: usize) -> SpeedLimit<Self> {
+        SpeedLimit::new(self, bytes_per_second)
+    }
+}
+
+impl<T> SpeedLimitExt for T where T: AsyncRead + Unpin {}
@@ -1,5 +1,17 @@
-use async_read_length_limit::{LengthLimit, LengthLimitExt};
-use futures_lite::{future::block_on, io::Cursor, AsyncReadExt};
+use async_read_length_limit::{
+    read_length_prefixed_frame, read_to_end_limited, skip_frame_padding, LengthLimit,
+    LengthLimitError, LengthLimitExt, LengthLimitWriteExt, MockClock, SpeedLimit,
+};
+use futures_lite::{
+    future::block_on, io::Cursor, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt,
+};
+use std::{
+    future::Future,
+    io::IoSliceMut,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
 
 const MAX_MEMORY_TO_ALLOCATE: usize = 1024 * 1024;
 const ITERATIONS: usize = 1000;
@@ -121,6 +133,7 @@ pub fn other_interfaces() {
         },
     },
     bytes_remaining: 100,
+    max_read_chunk: 524288,
 }"#,
             &format!("{length_limit:#?}")
         );
@@ -140,3 +153,222 @@ pub fn error() {
         assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
     });
 }
+
+#[test]
+pub fn write_under_limit() {
+    block_on(async {
+        let mut output = Vec::new();
+        let mut limited = output.limit_write_bytes(100);
+        let result = limited.write_all(b"these are the data").await;
+        assert!(result.is_ok());
+        output = limited.into_inner();
+        assert_eq!(output, b"these are the data");
+    });
+}
+
+#[test]
+pub fn write_over_limit() {
+    block_on(async {
+        let output = Vec::new();
+        let mut limited = output.limit_write_bytes(5);
+        let result = limited.write_all(b"these are the data").await;
+        let err = result.unwrap_err();
+        assert_eq!("Length limit exceeded", err.to_string());
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(limited.into_inner(), b"these");
+    });
+}
+
+#[test]
+pub fn read_vectored() {
+    block_on(async {
+        let cursor = Cursor::new(b"these are the input data");
+        let mut limited = cursor.limit_bytes(4);
+        let mut a = [0u8; 2];
+        let mut b = [0u8; 20];
+        let mut bufs = [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)];
+        let total = futures_lite::future::poll_fn(|cx| {
+            std::pin::Pin::new(&mut limited).poll_read_vectored(cx, &mut bufs[..])
+        })
+        .await
+        .unwrap();
+        assert_eq!(total, 4);
+        assert_eq!(&a, b"th");
+        assert_eq!(&b[..2], b"es");
+    });
+}
+
+#[test]
+pub fn buf_read() {
+    block_on(async {
+        let cursor = Cursor::new(b"these are the input data");
+        let mut limited = cursor.limit_bytes(5);
+
+        let buf = limited.fill_buf().await.unwrap().to_vec();
+        assert_eq!(buf, b"these");
+        limited.consume(buf.len());
+        assert_eq!(limited.bytes_remaining(), 0);
+
+        let result = limited.fill_buf().await;
+        assert!(result.is_err());
+    });
+}
+
+#[test]
+pub fn speed_limit_throttles_by_token_bucket() {
+    let clock = MockClock::new();
+    let cursor = Cursor::new(b"abcdefghij");
+    let mut limited = SpeedLimit::with_clock(cursor, 4, clock.clone());
+    let waker = Waker::noop();
+    let mut cx = Context::from_waker(waker);
+    let mut buf = [0u8; 10];
+
+    // the bucket starts full, so the first read is not throttled
+    let mut fut = limited.read(&mut buf);
+    match Pin::new(&mut fut).poll(&mut cx) {
+        Poll::Ready(Ok(4)) => {}
+        other => panic!("expected an immediate read of 4 bytes, got {other:?}"),
+    }
+    drop(fut);
+    assert_eq!(&buf[..4], b"abcd");
+
+    // the bucket is now empty, so the next read has to wait for it to refill
+    let mut fut = limited.read(&mut buf[4..]);
+    assert!(Pin::new(&mut fut).poll(&mut cx).is_pending());
+
+    // advancing the clock by a quarter second refills exactly one token at 4 bytes/sec
+    clock.advance(Duration::from_millis(250));
+    match Pin::new(&mut fut).poll(&mut cx) {
+        Poll::Ready(Ok(1)) => {}
+        other => panic!("expected a single byte once a token refilled, got {other:?}"),
+    }
+    assert_eq!(&buf[4..5], b"e");
+}
+
+#[test]
+pub fn frame_reads_exactly_the_declared_payload() {
+    block_on(async {
+        let mut bytes = Vec::from(5u64.to_be_bytes());
+        bytes.extend_from_slice(b"hellomore data that belongs to the next frame");
+        let cursor = Cursor::new(bytes);
+
+        let mut limited = read_length_prefixed_frame(cursor, 1024).await.unwrap();
+        let mut payload = vec![0; limited.bytes_remaining()];
+        limited.read_exact(&mut payload).await.unwrap();
+        assert_eq!(payload, b"hello");
+
+        let mut rest = Vec::new();
+        limited.into_inner().read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"more data that belongs to the next frame");
+    });
+}
+
+#[test]
+pub fn frame_rejects_oversized_length_prefix() {
+    block_on(async {
+        let bytes = Vec::from(1024u64.to_be_bytes());
+        let cursor = Cursor::new(bytes);
+
+        let err = read_length_prefixed_frame(cursor, 100).await.unwrap_err();
+        assert_eq!("Length limit exceeded", err.to_string());
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    });
+}
+
+#[test]
+pub fn frame_padding_is_skipped() {
+    block_on(async {
+        // a 5 byte payload, padded to the next 8 byte boundary
+        let mut bytes = Vec::from(5u64.to_be_bytes());
+        bytes.extend_from_slice(b"hello");
+        bytes.extend_from_slice(&[0; 3]);
+        bytes.extend_from_slice(b"next frame");
+        let cursor = Cursor::new(bytes);
+
+        let mut limited = read_length_prefixed_frame(cursor, 1024).await.unwrap();
+        let payload_len = limited.bytes_remaining();
+        let mut payload = vec![0; payload_len];
+        limited.read_exact(&mut payload).await.unwrap();
+        assert_eq!(payload, b"hello");
+
+        let mut reader = skip_frame_padding(limited.into_inner(), payload_len)
+            .await
+            .unwrap();
+
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"next frame");
+    });
+}
+
+struct ErrorAfter {
+    remaining: usize,
+}
+
+impl AsyncRead for ErrorAfter {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        if this.remaining == 0 {
+            return Poll::Ready(Err(std::io::Error::other("boom")));
+        }
+        let len = buf.len().min(this.remaining);
+        buf[..len].fill(b'x');
+        this.remaining -= len;
+        Poll::Ready(Ok(len))
+    }
+}
+
+#[test]
+pub fn read_to_end_limited_under_limit() {
+    block_on(async {
+        let cursor = Cursor::new(b"these are the input data");
+        let result = read_to_end_limited(cursor, 1024).await;
+        assert_eq!(result.unwrap(), b"these are the input data");
+    });
+}
+
+#[test]
+pub fn read_to_end_limited_over_limit() {
+    block_on(async {
+        let cursor = Cursor::new(b"these are the input data");
+        let err = read_to_end_limited(cursor, 5).await.unwrap_err();
+        assert!(matches!(err, LengthLimitError::Exceeded { limit: 5 }));
+        assert_eq!(err.to_string(), "length limit of 5 bytes exceeded");
+    });
+}
+
+#[test]
+pub fn read_to_end_limited_propagates_io_errors() {
+    block_on(async {
+        let reader = ErrorAfter { remaining: 3 };
+        let result = read_to_end_limited(reader, 1024).await;
+        match result {
+            Err(LengthLimitError::Io(err)) => assert_eq!(err.to_string(), "boom"),
+            other => panic!("expected a propagated io error, got {other:?}"),
+        }
+    });
+}
+
+#[test]
+pub fn max_read_chunk_defaults_to_512_kib() {
+    let limited = Cursor::new(b"").limit_bytes(1024);
+    assert_eq!(limited.max_read_chunk(), 512 * 1024);
+}
+
+#[test]
+pub fn with_max_read_chunk_is_honored_while_collecting() {
+    block_on(async {
+        let data: Vec<_> = std::iter::repeat_n(b'x', 10_000).collect();
+        let limited = Cursor::new(data.clone())
+            .limit_bytes(10_001)
+            .with_max_read_chunk(7);
+        assert_eq!(limited.max_read_chunk(), 7);
+
+        let collected = limited.collect().await.unwrap();
+        assert_eq!(collected, data);
+    });
+}